@@ -1,22 +1,32 @@
-use std::{collections::HashMap, error::Error, time::Duration};
+use std::{error::Error, sync::Arc, time::Duration};
 
 use clap::{App, Arg};
 use futures::{stream::FuturesUnordered, StreamExt};
 use governor::{Quota, RateLimiter};
-use regex::Regex;
 use reqwest::redirect;
 use tokio::{
+    io::{AsyncBufReadExt, BufReader},
     runtime::Builder,
     task,
 };
 
-use async_std::io;
-use async_std::io::prelude::*;
+mod pacing;
+mod policy;
+mod ratelimit;
+mod report;
+mod retry;
+mod signatures;
+
+use policy::{Outcome, ScanPolicy};
+use ratelimit::{GlobalLimiter, HostRateLimiters};
+use report::{DetectionRecord, OutputMode, Report};
+use retry::{RetriedResponse, RetryOutcome};
+use signatures::Signature;
 
 #[derive(Clone, Debug)]
 pub struct Job {
     ip_str: Option<String>,
-    patterns: Option<HashMap<i32, String>>,
+    signatures: Arc<Vec<Signature>>,
 }
 
 #[derive(Clone, Debug)]
@@ -67,6 +77,58 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
                 .display_order(5)
                 .help("The amount of workers"),
         )
+        .arg(
+            Arg::with_name("signatures")
+                .short('s')
+                .long("signatures")
+                .takes_value(true)
+                .display_order(6)
+                .help("Path to a JSON file of probe signatures (defaults to the bundled set)"),
+        )
+        .arg(
+            Arg::with_name("auto-tune")
+                .long("auto-tune")
+                .takes_value(false)
+                .display_order(7)
+                .help("Automatically back off the rate against hosts that start erroring"),
+        )
+        .arg(
+            Arg::with_name("auto-bail")
+                .long("auto-bail")
+                .takes_value(false)
+                .display_order(8)
+                .help("Stop scanning a host entirely once it is clearly unhealthy"),
+        )
+        .arg(
+            Arg::with_name("per-host-rate")
+                .long("per-host-rate")
+                .takes_value(true)
+                .default_value("10")
+                .display_order(9)
+                .help("Maximum in-flight requests per second against a single host"),
+        )
+        .arg(
+            Arg::with_name("retries")
+                .long("retries")
+                .takes_value(true)
+                .default_value("2")
+                .display_order(10)
+                .help("How many times to retry a probe on a transient failure"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .takes_value(false)
+                .display_order(11)
+                .help("Print a single JSON array of detections when the scan finishes"),
+        )
+        .arg(
+            Arg::with_name("jsonl")
+                .long("jsonl")
+                .takes_value(false)
+                .display_order(12)
+                .help("Print each detection as a JSON object, one per line"),
+        )
         .get_matches();
 
     let rate = match matches.value_of("rate").unwrap().parse::<u32>() {
@@ -98,9 +160,51 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
         }
     };
 
-    let mut patterns = HashMap::new();
-    patterns.insert(1, String::from(r"/content/dam.*"));
-    patterns.insert(2, String::from(r"/etc.clientlibs.*"));
+    let signatures = match matches.value_of("signatures") {
+        Some(path) => match signatures::load_signatures(path) {
+            Ok(sigs) => sigs,
+            Err(e) => {
+                println!("could not load signatures from {}: {}, using bundled defaults", path, e);
+                signatures::default_signatures()
+            }
+        },
+        None => signatures::default_signatures(),
+    };
+    let signatures = Arc::new(signatures);
+
+    let auto_tune = matches.is_present("auto-tune");
+    let auto_bail = matches.is_present("auto-bail");
+    let policy = Arc::new(ScanPolicy::new(auto_tune, auto_bail));
+
+    let per_host_rate = match matches.value_of("per-host-rate").unwrap().parse::<u32>() {
+        Ok(n) => n,
+        Err(_) => {
+            println!("{}", "could not parse per-host-rate, using default of 10");
+            10
+        }
+    };
+    let host_limiters = Arc::new(HostRateLimiters::new(per_host_rate));
+
+    let retries = match matches.value_of("retries").unwrap().parse::<u32>() {
+        Ok(n) => n,
+        Err(_) => {
+            println!("{}", "could not parse retries, using default of 2");
+            2
+        }
+    };
+
+    let output_mode = if matches.is_present("jsonl") {
+        OutputMode::Jsonl
+    } else if matches.is_present("json") {
+        OutputMode::Json
+    } else {
+        OutputMode::Text
+    };
+    let report = Arc::new(Report::new(output_mode));
+
+    let global_limiter: Arc<GlobalLimiter> = Arc::new(RateLimiter::direct(Quota::per_second(
+        std::num::NonZeroU32::new(rate).unwrap(),
+    )));
 
     // Set up a worker pool with the number of threads specified from the arguments
     let rt = Builder::new_multi_thread()
@@ -109,10 +213,43 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
         .build()
         .unwrap();
 
-    // job channels
-    let (job_tx, job_rx) = spmc::channel::<Job>();
+    let prune_limiters = host_limiters.clone();
+    rt.spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            prune_limiters.prune_idle();
+        }
+    });
+
+    // Ctrl-C / SIGTERM: flip a watch so `send_url` stops enqueuing new jobs.
+    // In-flight `run_detector` workers keep draining whatever is already
+    // queued, since the job channel only closes once `job_tx` is dropped.
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    rt.spawn(async move {
+        #[cfg(unix)]
+        {
+            let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("failed to install SIGTERM handler");
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = sigterm.recv() => {}
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+        let _ = shutdown_tx.send(true);
+    });
 
-    rt.spawn(async move { send_url(job_tx, patterns, rate).await });
+    // job channel: bounded async-channel, not spmc, so both the producer and
+    // every detector worker can await it cooperatively instead of blocking a
+    // runtime thread.
+    let (job_tx, job_rx) = async_channel::bounded::<Job>(concurrency as usize);
+
+    let send_policy = policy.clone();
+    rt.spawn(async move { send_url(job_tx, signatures, send_policy, shutdown_rx).await });
 
     // process the jobs
     let workers = FuturesUnordered::new();
@@ -120,31 +257,50 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     // process the jobs for scanning.
     for _ in 0..concurrency {
         let jrx = job_rx.clone();
+        let detector_policy = policy.clone();
+        let detector_limiters = host_limiters.clone();
+        let detector_report = report.clone();
+        let detector_global_limiter = global_limiter.clone();
         workers.push(task::spawn(async move {
             //  run the detector
-            run_detector(jrx, timeout).await
+            run_detector(
+                jrx,
+                timeout,
+                detector_policy,
+                detector_limiters,
+                detector_global_limiter,
+                retries,
+                detector_report,
+            )
+            .await
         }));
     }
     let _: Vec<_> = workers.collect().await;
     rt.shutdown_background();
 
+    report.finish();
+
     Ok(())
 }
 
 async fn send_url(
-    mut tx: spmc::Sender<Job>,
-    patterns: HashMap<i32, String>,
-    rate: u32,
+    tx: async_channel::Sender<Job>,
+    signatures: Arc<Vec<Signature>>,
+    policy: Arc<ScanPolicy>,
+    mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
 ) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    //set rate limit
-    let lim = RateLimiter::direct(Quota::per_second(std::num::NonZeroU32::new(rate).unwrap()));
-
     // send the jobs
-    let stdin = io::BufReader::new(io::stdin());
+    let stdin = BufReader::new(tokio::io::stdin());
     let mut lines = stdin.lines();
-    while let Some(line) = lines.next().await {
-        lim.until_ready().await;
-        let host_line = line.unwrap();
+    loop {
+        let host_line = tokio::select! {
+            line = lines.next_line() => match line? {
+                Some(host_line) => host_line,
+                None => break,
+            },
+            _ = shutdown_rx.changed() => break,
+        };
+
         let mut host = String::from("");
         let url = match reqwest::Url::parse(&host_line) {
             Ok(url) => url,
@@ -157,19 +313,32 @@ async fn send_url(
             None => continue,
         };
         host.push_str(host_str);
+
+        if policy.is_abandoned(&host) {
+            continue;
+        }
+
         let msg = Job {
             ip_str: Some(host.to_string().clone()),
-            patterns: Some(patterns.clone()),
+            signatures: signatures.clone(),
         };
-        if let Err(_) = tx.send(msg) {
-            continue;
+        if tx.send(msg).await.is_err() {
+            break;
         }
     }
     Ok(())
 }
 
 // this function will test perform the aem detection
-pub async fn run_detector(rx: spmc::Receiver<Job>, timeout: usize) {
+pub async fn run_detector(
+    rx: async_channel::Receiver<Job>,
+    timeout: usize,
+    policy: Arc<ScanPolicy>,
+    host_limiters: Arc<HostRateLimiters>,
+    global_limiter: Arc<GlobalLimiter>,
+    retries: u32,
+    report: Arc<Report>,
+) {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(
         reqwest::header::USER_AGENT,
@@ -188,36 +357,71 @@ pub async fn run_detector(rx: spmc::Receiver<Job>, timeout: usize) {
         .build()
         .unwrap();
 
-    while let Ok(job) = rx.recv() {
+    loop {
+        let job = tokio::select! {
+            job = rx.recv() => match job {
+                Ok(job) => job,
+                Err(_) => break,
+            },
+        };
+
         let job_host = job.ip_str.unwrap();
-        let job_patterns = job.patterns.unwrap();
-        for pattern in job_patterns {
-            let job_host_new = job_host.clone();
-            let get = client.get(job_host_new);
-            let req = match get.build() {
-                Ok(req) => req,
-                Err(_) => {
-                    continue;
-                }
-            };
-            let resp = match client.execute(req).await {
-                Ok(resp) => resp,
-                Err(_) => {
-                    continue;
-                }
-            };
-            let body = match resp.text().await {
-                Ok(body) => body,
-                Err(_) => {
-                    continue;
-                }
-            };
+        report.record_host_scanned();
+        let mut hits = Vec::new();
+        for signature in job.signatures.iter() {
+            if policy.is_abandoned(&job_host) {
+                break;
+            }
+
+            report.record_request();
+
+            let probe_url = format!("{}{}", job_host, signature.path);
+            let RetriedResponse { status, headers, body } = match retry::get_with_retry(
+                &client,
+                &probe_url,
+                retries,
+                &global_limiter,
+                &host_limiters,
+                &policy,
+                &job_host,
+            )
+            .await
+            {
+                    RetryOutcome::Success(resp) => resp,
+                    RetryOutcome::GaveUp { attempts, timed_out } => {
+                        eprintln!(
+                            "{}{}: gave up after {} retries",
+                            job_host, signature.path, attempts
+                        );
+                        if timed_out {
+                            policy.record(&job_host, Outcome::Timeout);
+                            report.record_timeout();
+                        } else {
+                            policy.record(&job_host, Outcome::ConnError);
+                            report.record_error();
+                        }
+                        continue;
+                    }
+                };
+
+            policy.record(&job_host, Outcome::Status(status));
 
-            let re = Regex::new(&pattern.1).unwrap();
-            if re.is_match(&body) {
-                println!("{}", job_host);
-                continue;
+            if let Some(hit) = signature.evaluate(status, &body, &headers) {
+                hits.push(hit);
             }
         }
+
+        if !hits.is_empty() {
+            let record = DetectionRecord {
+                host: job_host.clone(),
+                matched_paths: hits.iter().map(|h| h.path.clone()).collect(),
+                status_codes: hits.iter().map(|h| h.status).collect(),
+                evidence: hits
+                    .iter()
+                    .map(|h| format!("confidence {:.0}%", h.confidence * 100.0))
+                    .collect(),
+            };
+            report.record_detection(record);
+        }
     }
 }
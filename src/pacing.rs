@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use crate::policy::ScanPolicy;
+use crate::ratelimit::{GlobalLimiter, HostRateLimiters};
+
+/// Pace a single outgoing request against `host`: honor any auto-tune
+/// throttle on top of the configured per-host rate, then wait for both the
+/// global and per-host token buckets. Called before *every* attempt of a
+/// probe, including retries, so a host that starts erroring gets backed off
+/// consistently rather than only on its first try.
+pub async fn pace(
+    global_limiter: &GlobalLimiter,
+    host_limiters: &HostRateLimiters,
+    policy: &ScanPolicy,
+    host: &str,
+) {
+    let factor = policy.rate_factor(host);
+    if factor < 1.0 {
+        let extra_ms = ((1.0 - factor) / factor.max(0.05)) * (1000.0 / host_limiters.rate() as f32);
+        tokio::time::sleep(Duration::from_millis(extra_ms as u64)).await;
+    }
+
+    global_limiter.until_ready().await;
+    host_limiters.until_ready(host).await;
+}
@@ -0,0 +1,157 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The window size (in requests) used to compute a host's rolling error ratio.
+const WINDOW_SIZE: usize = 50;
+/// Error ratio above which auto-tune halves the effective rate for a host.
+const AUTO_TUNE_THRESHOLD: f32 = 0.25;
+/// Consecutive 429s that also trigger auto-tune, independent of the ratio.
+const CONSECUTIVE_429_THRESHOLD: u32 = 10;
+/// Error ratio at or above which auto-bail gives up on a host entirely.
+const AUTO_BAIL_THRESHOLD: f32 = 0.6;
+/// How long a throttled host is left alone before its rate is stepped back up.
+const COOLDOWN: Duration = Duration::from_secs(5);
+/// How much the rate factor is stepped back up per clean cooldown window.
+const RECOVERY_STEP: f32 = 0.1;
+
+/// What happened when a probe was executed against a host.
+#[derive(Clone, Copy, Debug)]
+pub enum Outcome {
+    Timeout,
+    ConnError,
+    Status(u16),
+}
+
+impl Outcome {
+    fn is_error(&self) -> bool {
+        match self {
+            Outcome::Timeout | Outcome::ConnError => true,
+            Outcome::Status(code) => matches!(*code, 403 | 429 | 503),
+        }
+    }
+
+    fn is_429(&self) -> bool {
+        matches!(self, Outcome::Status(429))
+    }
+}
+
+struct HostStats {
+    window: VecDeque<Outcome>,
+    consecutive_429: u32,
+    /// Fraction of the configured rate this host is currently allowed, in
+    /// (0.0, 1.0]. 1.0 means no throttling is in effect.
+    rate_factor: f32,
+    throttled_since: Option<Instant>,
+    abandoned: bool,
+}
+
+impl HostStats {
+    fn new() -> Self {
+        HostStats {
+            window: VecDeque::with_capacity(WINDOW_SIZE),
+            consecutive_429: 0,
+            rate_factor: 1.0,
+            throttled_since: None,
+            abandoned: false,
+        }
+    }
+
+    fn error_ratio(&self) -> f32 {
+        if self.window.is_empty() {
+            return 0.0;
+        }
+        let errors = self.window.iter().filter(|o| o.is_error()).count();
+        errors as f32 / self.window.len() as f32
+    }
+}
+
+/// Tracks per-host error statistics and derives live rate adjustments from
+/// them. Shared between the `send_url` producer (which checks `is_abandoned`
+/// before enqueuing more probes for a host) and `run_detector` consumers
+/// (which call `record` after every request and `rate_factor` before
+/// pacing the next one).
+pub struct ScanPolicy {
+    auto_tune: bool,
+    auto_bail: bool,
+    stats: Mutex<HashMap<String, HostStats>>,
+}
+
+impl ScanPolicy {
+    pub fn new(auto_tune: bool, auto_bail: bool) -> Self {
+        ScanPolicy {
+            auto_tune,
+            auto_bail,
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record the outcome of a request against `host` and update its
+    /// throttle/abandon state accordingly.
+    pub fn record(&self, host: &str, outcome: Outcome) {
+        if !self.auto_tune && !self.auto_bail {
+            return;
+        }
+
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(host.to_string()).or_insert_with(HostStats::new);
+
+        if entry.window.len() == WINDOW_SIZE {
+            entry.window.pop_front();
+        }
+        entry.window.push_back(outcome);
+
+        if outcome.is_429() {
+            entry.consecutive_429 += 1;
+        } else {
+            entry.consecutive_429 = 0;
+        }
+
+        let ratio = entry.error_ratio();
+
+        if self.auto_bail && ratio >= AUTO_BAIL_THRESHOLD {
+            entry.abandoned = true;
+            return;
+        }
+
+        if !self.auto_tune {
+            return;
+        }
+
+        let should_throttle =
+            ratio > AUTO_TUNE_THRESHOLD || entry.consecutive_429 >= CONSECUTIVE_429_THRESHOLD;
+
+        if should_throttle {
+            entry.rate_factor = (entry.rate_factor / 2.0).max(0.05);
+            entry.throttled_since = Some(Instant::now());
+        } else if let Some(since) = entry.throttled_since {
+            if since.elapsed() >= COOLDOWN && entry.rate_factor < 1.0 {
+                entry.rate_factor = (entry.rate_factor + RECOVERY_STEP).min(1.0);
+                entry.throttled_since = Some(Instant::now());
+            }
+        }
+    }
+
+    /// The fraction of the user-configured rate that `host` should currently
+    /// be allowed. 1.0 when no throttling is active for it.
+    pub fn rate_factor(&self, host: &str) -> f32 {
+        self.stats
+            .lock()
+            .unwrap()
+            .get(host)
+            .map(|s| s.rate_factor)
+            .unwrap_or(1.0)
+    }
+
+    /// Whether auto-bail has given up on this host entirely.
+    pub fn is_abandoned(&self, host: &str) -> bool {
+        self.auto_bail
+            && self
+                .stats
+                .lock()
+                .unwrap()
+                .get(host)
+                .map(|s| s.abandoned)
+                .unwrap_or(false)
+    }
+}
@@ -0,0 +1,71 @@
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use governor::{Quota, RateLimiter};
+
+type HostLimiter = RateLimiter<
+    governor::state::direct::NotKeyed,
+    governor::state::InMemoryState,
+    governor::clock::DefaultClock,
+>;
+
+/// The single aggregate rate limiter, shared by every detector/retry
+/// attempt, so `--rate` bounds the whole scan's request rate
+/// rather than just how fast hosts are dispatched.
+pub type GlobalLimiter = HostLimiter;
+
+/// How long a host's bucket can sit idle before it's swept, to bound memory
+/// on long target lists.
+const IDLE_TTL: Duration = Duration::from_secs(300);
+
+/// Per-host token buckets, keyed by host, on top of (but independent of) the
+/// single global rate limiter. Buckets are created lazily the first time a
+/// host is seen and pruned once they've been idle for `IDLE_TTL`.
+pub struct HostRateLimiters {
+    per_host_rate: u32,
+    buckets: DashMap<String, (Arc<HostLimiter>, Instant)>,
+}
+
+impl HostRateLimiters {
+    pub fn new(per_host_rate: u32) -> Self {
+        HostRateLimiters {
+            per_host_rate,
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// The configured per-host rate, used by callers that need to scale an
+    /// additional delay (e.g. auto-tune) relative to it.
+    pub fn rate(&self) -> u32 {
+        self.per_host_rate
+    }
+
+    /// Wait until `host`'s own bucket allows another request, creating the
+    /// bucket on first sight.
+    pub async fn until_ready(&self, host: &str) {
+        let limiter = {
+            let mut entry = self
+                .buckets
+                .entry(host.to_string())
+                .or_insert_with(|| (Arc::new(Self::new_bucket(self.per_host_rate)), Instant::now()));
+            entry.1 = Instant::now();
+            entry.0.clone()
+        };
+        limiter.until_ready().await;
+    }
+
+    fn new_bucket(rate: u32) -> HostLimiter {
+        let quota = Quota::per_second(NonZeroU32::new(rate.max(1)).unwrap());
+        RateLimiter::direct(quota)
+    }
+
+    /// Drop buckets for hosts that haven't been touched in `IDLE_TTL`. Meant
+    /// to be called periodically from a background task.
+    pub fn prune_idle(&self) {
+        let now = Instant::now();
+        self.buckets
+            .retain(|_, (_, last_seen)| now.duration_since(*last_seen) < IDLE_TTL);
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// A single positive AEM detection, suitable for `--json`/`--jsonl` output.
+#[derive(Clone, Debug, Serialize)]
+pub struct DetectionRecord {
+    pub host: String,
+    pub matched_paths: Vec<String>,
+    pub status_codes: Vec<u16>,
+    pub evidence: Vec<String>,
+}
+
+/// Where positive detections go: plain `println!`, one JSON object per line,
+/// or a single JSON array printed once the scan finishes.
+pub enum OutputMode {
+    Text,
+    Json,
+    Jsonl,
+}
+
+/// Collects scan-wide counters and (depending on `OutputMode`) detection
+/// records, shared across every `run_detector` worker. Counters are updated
+/// on the hot path so they're plain atomics; the handful of per-run
+/// collections use a `Mutex` the same way `policy::ScanPolicy` does.
+pub struct Report {
+    mode: OutputMode,
+    started_at: Instant,
+    hosts_scanned: AtomicU64,
+    total_requests: AtomicU64,
+    aem_positive: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    signature_hits: Mutex<HashMap<String, u64>>,
+    records: Mutex<Vec<DetectionRecord>>,
+}
+
+impl Report {
+    pub fn new(mode: OutputMode) -> Self {
+        Report {
+            mode,
+            started_at: Instant::now(),
+            hosts_scanned: AtomicU64::new(0),
+            total_requests: AtomicU64::new(0),
+            aem_positive: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            timeouts: AtomicU64::new(0),
+            signature_hits: Mutex::new(HashMap::new()),
+            records: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn record_host_scanned(&self) {
+        self.hosts_scanned.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_request(&self) {
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_timeout(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Emit a positive detection, formatting it according to the configured
+    /// output mode.
+    pub fn record_detection(&self, record: DetectionRecord) {
+        self.aem_positive.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut hits = self.signature_hits.lock().unwrap();
+            for path in &record.matched_paths {
+                *hits.entry(path.clone()).or_insert(0) += 1;
+            }
+        }
+
+        match self.mode {
+            OutputMode::Text => {
+                println!("{} is running AEM, signatures matched:", record.host);
+                for (path, status, evidence) in record
+                    .matched_paths
+                    .iter()
+                    .zip(record.status_codes.iter())
+                    .zip(record.evidence.iter())
+                    .map(|((p, s), e)| (p, s, e))
+                {
+                    println!("  - {} (status {}) {}", path, status, evidence);
+                }
+            }
+            OutputMode::Jsonl => {
+                if let Ok(line) = serde_json::to_string(&record) {
+                    println!("{}", line);
+                }
+            }
+            OutputMode::Json => {
+                self.records.lock().unwrap().push(record);
+            }
+        }
+    }
+
+    /// Print the final summary and, for `--json`, the buffered detection
+    /// array. Called once all workers have drained.
+    pub fn finish(&self) {
+        if let OutputMode::Json = self.mode {
+            let records = self.records.lock().unwrap();
+            if let Ok(json) = serde_json::to_string_pretty(&*records) {
+                println!("{}", json);
+            }
+        }
+
+        let elapsed = self.started_at.elapsed();
+        let total_requests = self.total_requests.load(Ordering::Relaxed);
+        let rate = if elapsed.as_secs_f64() > 0.0 {
+            total_requests as f64 / elapsed.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        eprintln!("--- scan summary ---");
+        eprintln!("hosts scanned:   {}", self.hosts_scanned.load(Ordering::Relaxed));
+        eprintln!("aem positive:    {}", self.aem_positive.load(Ordering::Relaxed));
+        eprintln!("errors:          {}", self.errors.load(Ordering::Relaxed));
+        eprintln!("timeouts:        {}", self.timeouts.load(Ordering::Relaxed));
+        eprintln!("elapsed:         {:.2}s", elapsed.as_secs_f64());
+        eprintln!("effective req/s: {:.1}", rate);
+        eprintln!("signature hits:");
+        for (path, count) in self.signature_hits.lock().unwrap().iter() {
+            eprintln!("  {} => {}", path, count);
+        }
+    }
+}
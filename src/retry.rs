@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use crate::pacing;
+use crate::policy::ScanPolicy;
+use crate::ratelimit::{GlobalLimiter, HostRateLimiters};
+
+/// Whether a failure is worth retrying.
+enum Failure {
+    Transient,
+    Permanent,
+}
+
+/// A successfully read response: status, headers and body all came back
+/// intact (though the status itself may still be an error, including a
+/// 429/5xx that was still returned on the last allowed retry).
+pub struct RetriedResponse {
+    pub status: u16,
+    pub headers: reqwest::header::HeaderMap,
+    pub body: String,
+}
+
+/// The result of running a request through the retry loop.
+pub enum RetryOutcome {
+    Success(RetriedResponse),
+    /// The request or body read failed and was not retried further: either
+    /// the failure was permanent, or every allowed retry was used. A 429/5xx
+    /// status never produces this variant; it comes back as `Success`.
+    /// `timed_out` distinguishes a request/body-read timeout from other
+    /// failures (connection resets, DNS errors), so callers can report it as such.
+    GaveUp { attempts: u32, timed_out: bool },
+}
+
+fn classify_status(status: u16) -> Failure {
+    match status {
+        429 | 500..=599 => Failure::Transient,
+        _ => Failure::Permanent,
+    }
+}
+
+fn classify_request_error(e: &reqwest::Error) -> Failure {
+    if e.is_timeout() {
+        return Failure::Transient;
+    }
+    if e.is_connect() {
+        // reqwest doesn't expose a DNS-vs-reset distinction directly, so fall
+        // back to sniffing the underlying io error message.
+        if e.to_string().to_lowercase().contains("dns") {
+            return Failure::Permanent;
+        }
+        return Failure::Transient;
+    }
+    Failure::Transient
+}
+
+/// Exponential backoff with jitter: base 200ms, doubling per attempt, capped
+/// at 3.2s, plus up to half that again at random.
+fn backoff(attempt: u32) -> Duration {
+    let capped_ms = 200u64.saturating_mul(1u64 << attempt.min(4)).min(3200);
+    let jitter_ms = rand::random::<u64>() % (capped_ms / 2 + 1);
+    Duration::from_millis(capped_ms + jitter_ms)
+}
+
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    let value = resp.headers().get(reqwest::header::RETRY_AFTER)?;
+    let seconds = value.to_str().ok()?.parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Execute a GET against `url`, retrying transient failures (timeouts,
+/// connection resets, 429/5xx, and body-read failures) with exponential
+/// backoff up to `max_retries` times. Permanent failures (DNS errors, 404s,
+/// ...) are returned as-is on the first attempt. The whole request/response
+/// cycle, including reading the body, happens inside the retry loop so a
+/// dropped connection mid-body-read gets retried from scratch just like a
+/// failed `client.execute`. Every attempt, including retries, is paced
+/// through `global_limiter`/`host_limiters`/`policy` first, so a host that
+/// starts erroring stays capped at its throttled rate instead of being hit
+/// again the moment its backoff expires.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+    max_retries: u32,
+    global_limiter: &GlobalLimiter,
+    host_limiters: &HostRateLimiters,
+    policy: &ScanPolicy,
+    host: &str,
+) -> RetryOutcome {
+    let mut attempt = 0;
+    loop {
+        pacing::pace(global_limiter, host_limiters, policy, host).await;
+
+        let req = match client.get(url).build() {
+            Ok(req) => req,
+            Err(_) => {
+                return RetryOutcome::GaveUp {
+                    attempts: attempt,
+                    timed_out: false,
+                }
+            }
+        };
+
+        let resp = match client.execute(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                let timed_out = e.is_timeout();
+                let retryable =
+                    matches!(classify_request_error(&e), Failure::Transient) && attempt < max_retries;
+                if !retryable {
+                    return RetryOutcome::GaveUp {
+                        attempts: attempt,
+                        timed_out,
+                    };
+                }
+                attempt += 1;
+                tokio::time::sleep(backoff(attempt)).await;
+                continue;
+            }
+        };
+
+        let status = resp.status().as_u16();
+        if matches!(classify_status(status), Failure::Transient) && attempt < max_retries {
+            let wait = retry_after(&resp).unwrap_or_else(|| backoff(attempt));
+            attempt += 1;
+            tokio::time::sleep(wait).await;
+            continue;
+        }
+
+        let headers = resp.headers().clone();
+        match resp.text().await {
+            Ok(body) => return RetryOutcome::Success(RetriedResponse { status, headers, body }),
+            Err(e) => {
+                let timed_out = e.is_timeout();
+                if attempt < max_retries {
+                    attempt += 1;
+                    tokio::time::sleep(backoff(attempt)).await;
+                    continue;
+                }
+                return RetryOutcome::GaveUp {
+                    attempts: attempt,
+                    timed_out,
+                };
+            }
+        }
+    }
+}
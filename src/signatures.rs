@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+/// A single forced-browsing probe against a confirmed-AEM endpoint.
+///
+/// At least one of `expected_status`, `body_regex` or `match_on_header` should
+/// be set, otherwise the probe can never produce a match.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Signature {
+    pub path: String,
+    #[serde(default)]
+    pub expected_status: Option<u16>,
+    #[serde(default)]
+    pub body_regex: Option<String>,
+    #[serde(default)]
+    pub match_on_header: Option<(String, String)>,
+}
+
+/// The outcome of probing a single signature against a target.
+#[derive(Clone, Debug)]
+pub struct SignatureHit {
+    pub path: String,
+    pub status: u16,
+    /// Fraction of the signature's configured criteria that matched.
+    pub confidence: f32,
+}
+
+impl Signature {
+    /// How many independent criteria this signature defines (status, body,
+    /// header). Used as the denominator for confidence scoring.
+    fn criteria_count(&self) -> u32 {
+        self.expected_status.is_some() as u32
+            + self.body_regex.is_some() as u32
+            + self.match_on_header.is_some() as u32
+    }
+
+    /// Score a response against this signature, returning a hit if at least
+    /// one configured criterion matched.
+    pub fn evaluate(
+        &self,
+        status: u16,
+        body: &str,
+        headers: &reqwest::header::HeaderMap,
+    ) -> Option<SignatureHit> {
+        let total = self.criteria_count();
+        if total == 0 {
+            return None;
+        }
+
+        let mut matched = 0u32;
+
+        if let Some(expected) = self.expected_status {
+            if expected == status {
+                matched += 1;
+            }
+        }
+
+        if let Some(pattern) = &self.body_regex {
+            if let Ok(re) = regex::Regex::new(pattern) {
+                if re.is_match(body) {
+                    matched += 1;
+                }
+            }
+        }
+
+        if let Some((name, needle)) = &self.match_on_header {
+            if let Some(value) = headers.get(name) {
+                if value.to_str().unwrap_or("").contains(needle.as_str()) {
+                    matched += 1;
+                }
+            }
+        }
+
+        if matched == 0 {
+            return None;
+        }
+
+        Some(SignatureHit {
+            path: self.path.clone(),
+            status,
+            confidence: matched as f32 / total as f32,
+        })
+    }
+}
+
+/// The bundled set of signatures, used when `--signatures` is not given.
+pub fn default_signatures() -> Vec<Signature> {
+    vec![
+        Signature {
+            path: "/crx/de/index.jsp".to_string(),
+            expected_status: Some(200),
+            body_regex: Some(r"CRXDE Lite".to_string()),
+            match_on_header: None,
+        },
+        Signature {
+            path: "/system/console/bundles".to_string(),
+            expected_status: Some(200),
+            body_regex: Some(r"Apache Felix".to_string()),
+            match_on_header: None,
+        },
+        Signature {
+            path: "/libs/granite/core/content/login.html".to_string(),
+            expected_status: Some(200),
+            body_regex: Some(r"granite\.csrf\.token|AEM Sign In".to_string()),
+            match_on_header: None,
+        },
+        Signature {
+            path: "/content/dam.json".to_string(),
+            expected_status: None,
+            body_regex: Some(r"/content/dam.*".to_string()),
+            match_on_header: None,
+        },
+        Signature {
+            path: "/etc.clientlibs".to_string(),
+            expected_status: None,
+            body_regex: Some(r"/etc.clientlibs.*".to_string()),
+            match_on_header: None,
+        },
+    ]
+}
+
+/// Load a signature wordlist from an external JSON file. Returns an error if
+/// the file can't be read or parsed; no defaults are substituted here.
+pub fn load_signatures(path: &str) -> Result<Vec<Signature>, Box<dyn Error + Send + Sync + 'static>> {
+    let raw = fs::read_to_string(path)?;
+    let signatures: Vec<Signature> = serde_json::from_str(&raw)?;
+    Ok(signatures)
+}